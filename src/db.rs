@@ -0,0 +1,186 @@
+// --- Persistent report storage ---
+//
+// Replaces the old `Mutex<HashMap<String, Vec<(String, String)>>>` in
+// `AppState`, which lost every record on restart and serialized all requests
+// behind a single lock. Reports now live in a `reports` table reached through
+// a connection pool, so reads no longer block on writes and data survives a
+// restart. SQLite is the default backend; enabling the `postgres` feature
+// flag switches the pool and SQL dialect to Postgres without touching the
+// call sites in `main.rs`.
+
+#[cfg(feature = "postgres")]
+pub type DbPool = sqlx::PgPool;
+
+#[cfg(not(feature = "postgres"))]
+pub type DbPool = sqlx::SqlitePool;
+
+/// One persisted (wallet, report) link.
+#[derive(Debug, Clone)]
+pub struct ReportRow {
+    pub cid: String,
+    pub file_name: String,
+    pub uploaded_at: String,
+    pub nonce: Option<String>,
+    pub thumbnail_cid: Option<String>,
+    pub blurhash: Option<String>,
+}
+
+/// Connect to `database_url` (read from `.env`/the environment by the
+/// caller) and ensure the `reports` table exists.
+pub async fn init_pool(database_url: &str) -> Result<DbPool, sqlx::Error> {
+    #[cfg(feature = "postgres")]
+    let pool = sqlx::postgres::PgPoolOptions::new()
+        .max_connections(5)
+        .connect(database_url)
+        .await?;
+
+    #[cfg(not(feature = "postgres"))]
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect(database_url)
+        .await?;
+
+    run_migrations(&pool).await?;
+    Ok(pool)
+}
+
+async fn run_migrations(pool: &DbPool) -> Result<(), sqlx::Error> {
+    // `uploaded_at` is whole-second epoch time, so two uploads for the same
+    // (wallet, cid, file_name) within the same second would collide on a
+    // composite key built from it — and by the time `insert_report` runs,
+    // the file is already pinned and the Solana transaction already paid
+    // for, so failing the insert would silently orphan that on-chain
+    // record. A surrogate autoincrement id sidesteps the collision instead
+    // of relying on `uploaded_at` for uniqueness.
+    let create_table_sql = if cfg!(feature = "postgres") {
+        "CREATE TABLE IF NOT EXISTS reports (
+            id BIGSERIAL PRIMARY KEY,
+            wallet_address TEXT NOT NULL,
+            cid TEXT NOT NULL,
+            file_name TEXT NOT NULL,
+            uploaded_at TEXT NOT NULL,
+            nonce TEXT,
+            thumbnail_cid TEXT,
+            blurhash TEXT
+        )"
+    } else {
+        "CREATE TABLE IF NOT EXISTS reports (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            wallet_address TEXT NOT NULL,
+            cid TEXT NOT NULL,
+            file_name TEXT NOT NULL,
+            uploaded_at TEXT NOT NULL,
+            nonce TEXT,
+            thumbnail_cid TEXT,
+            blurhash TEXT
+        )"
+    };
+
+    sqlx::query(create_table_sql).execute(pool).await?;
+    Ok(())
+}
+
+fn insert_sql() -> &'static str {
+    if cfg!(feature = "postgres") {
+        "INSERT INTO reports (wallet_address, cid, file_name, uploaded_at, nonce, thumbnail_cid, blurhash) VALUES ($1, $2, $3, $4, $5, $6, $7)"
+    } else {
+        "INSERT INTO reports (wallet_address, cid, file_name, uploaded_at, nonce, thumbnail_cid, blurhash) VALUES (?, ?, ?, ?, ?, ?, ?)"
+    }
+}
+
+fn select_sql() -> &'static str {
+    if cfg!(feature = "postgres") {
+        "SELECT cid, file_name, uploaded_at, nonce, thumbnail_cid, blurhash FROM reports WHERE wallet_address = $1 ORDER BY uploaded_at ASC"
+    } else {
+        "SELECT cid, file_name, uploaded_at, nonce, thumbnail_cid, blurhash FROM reports WHERE wallet_address = ? ORDER BY uploaded_at ASC"
+    }
+}
+
+/// Record a newly uploaded report for `wallet_address`.
+#[allow(clippy::too_many_arguments)]
+pub async fn insert_report(
+    pool: &DbPool,
+    wallet_address: &str,
+    cid: &str,
+    file_name: &str,
+    uploaded_at: &str,
+    nonce: Option<&str>,
+    thumbnail_cid: Option<&str>,
+    blurhash: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(insert_sql())
+        .bind(wallet_address)
+        .bind(cid)
+        .bind(file_name)
+        .bind(uploaded_at)
+        .bind(nonce)
+        .bind(thumbnail_cid)
+        .bind(blurhash)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Fetch every report recorded for `wallet_address`, oldest first.
+pub async fn fetch_reports(pool: &DbPool, wallet_address: &str) -> Result<Vec<ReportRow>, sqlx::Error> {
+    use sqlx::Row;
+
+    let rows = sqlx::query(select_sql())
+        .bind(wallet_address)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ReportRow {
+            cid: row.get("cid"),
+            file_name: row.get("file_name"),
+            uploaded_at: row.get("uploaded_at"),
+            nonce: row.get("nonce"),
+            thumbnail_cid: row.get("thumbnail_cid"),
+            blurhash: row.get("blurhash"),
+        })
+        .collect())
+}
+
+#[cfg(all(test, not(feature = "postgres")))]
+mod tests {
+    use super::*;
+
+    async fn in_memory_pool() -> DbPool {
+        init_pool("sqlite::memory:").await.expect("in-memory sqlite should connect")
+    }
+
+    #[tokio::test]
+    async fn insert_then_fetch_round_trips_a_report() {
+        let pool = in_memory_pool().await;
+
+        insert_report(&pool, "wallet-1", "Qm123", "scan.pdf", "2026-01-01T00:00:00Z", None, None, None)
+            .await
+            .expect("insert should succeed");
+
+        let reports = fetch_reports(&pool, "wallet-1").await.expect("fetch should succeed");
+
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].cid, "Qm123");
+        assert_eq!(reports[0].file_name, "scan.pdf");
+        assert_eq!(reports[0].nonce, None);
+    }
+
+    #[tokio::test]
+    async fn fetch_is_scoped_to_the_requested_wallet() {
+        let pool = in_memory_pool().await;
+
+        insert_report(&pool, "wallet-1", "QmA", "a.pdf", "2026-01-01T00:00:00Z", None, None, None)
+            .await
+            .unwrap();
+        insert_report(&pool, "wallet-2", "QmB", "b.pdf", "2026-01-01T00:00:01Z", None, None, None)
+            .await
+            .unwrap();
+
+        let reports = fetch_reports(&pool, "wallet-2").await.unwrap();
+
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].cid, "QmB");
+    }
+}
@@ -0,0 +1,192 @@
+// --- Upload ingest: type sniffing, metadata stripping, preview generation ---
+//
+// Runs on plaintext uploads only (encrypted uploads bypass this entirely,
+// since the server never sees their plaintext). Confirms the bytes are one
+// of the medical-report types we accept, strips embedded image metadata
+// (EXIF/GPS/device serials routinely leak patient-identifying info) by
+// re-encoding through `image`, and produces a small thumbnail + blurhash for
+// a fast gallery view.
+
+use std::env;
+use std::fmt;
+use std::io::Cursor;
+
+const DEFAULT_MAX_FILE_SIZE_BYTES: usize = 25 * 1024 * 1024;
+const DEFAULT_ALLOWED_TYPES: &str = "pdf,png,jpeg,dicom";
+const THUMBNAIL_MAX_DIMENSION: u32 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFileType {
+    Pdf,
+    Png,
+    Jpeg,
+    Dicom,
+}
+
+impl ReportFileType {
+    fn matches_env_name(self, name: &str) -> bool {
+        match self {
+            ReportFileType::Pdf => name == "pdf",
+            ReportFileType::Png => name == "png",
+            ReportFileType::Jpeg => name == "jpeg" || name == "jpg",
+            ReportFileType::Dicom => name == "dicom",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum IngestError {
+    TooLarge { max_bytes: usize },
+    UnsupportedType,
+    ImageProcessing(String),
+}
+
+impl fmt::Display for IngestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IngestError::TooLarge { max_bytes } => {
+                write!(f, "file exceeds the {}-byte upload limit", max_bytes)
+            }
+            IngestError::UnsupportedType => {
+                write!(f, "unsupported file type; allowed types are PDF, PNG, JPEG, DICOM")
+            }
+            IngestError::ImageProcessing(msg) => write!(f, "failed to process image: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for IngestError {}
+
+/// A thumbnail pinned alongside the main report, plus the blurhash placeholder
+/// the gallery can render before the thumbnail itself has loaded.
+pub struct ThumbnailAsset {
+    pub bytes: Vec<u8>,
+    pub blurhash: String,
+}
+
+pub struct IngestedFile {
+    /// The bytes to pin as the main report: unchanged for PDF/DICOM, or the
+    /// metadata-stripped re-encode for PNG/JPEG.
+    pub bytes: Vec<u8>,
+    pub thumbnail: Option<ThumbnailAsset>,
+}
+
+/// The configured upload size cap. Exposed so callers that bypass `ingest`
+/// entirely (the encrypted upload path, which never inspects plaintext)
+/// can still enforce it against the ciphertext they do receive.
+pub fn max_file_size_bytes() -> usize {
+    env::var("MAX_UPLOAD_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_FILE_SIZE_BYTES)
+}
+
+fn allowed_types() -> Vec<String> {
+    env::var("ALLOWED_REPORT_TYPES")
+        .unwrap_or_else(|_| DEFAULT_ALLOWED_TYPES.to_string())
+        .split(',')
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Sniff the magic bytes of `bytes` to identify one of the accepted medical
+/// report types. Returns `None` for anything else.
+pub fn sniff_file_type(bytes: &[u8]) -> Option<ReportFileType> {
+    if bytes.starts_with(b"%PDF-") {
+        return Some(ReportFileType::Pdf);
+    }
+    if bytes.starts_with(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]) {
+        return Some(ReportFileType::Png);
+    }
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some(ReportFileType::Jpeg);
+    }
+    // DICOM files carry a 128-byte preamble followed by the "DICM" magic word.
+    if bytes.len() > 132 && &bytes[128..132] == b"DICM" {
+        return Some(ReportFileType::Dicom);
+    }
+    None
+}
+
+/// Validate, strip metadata from, and generate a preview for an uploaded
+/// file. Only called for plaintext uploads.
+pub fn ingest(bytes: Vec<u8>) -> Result<IngestedFile, IngestError> {
+    let max_bytes = max_file_size_bytes();
+    if bytes.len() > max_bytes {
+        return Err(IngestError::TooLarge { max_bytes });
+    }
+
+    let file_type = sniff_file_type(&bytes).ok_or(IngestError::UnsupportedType)?;
+    let allowed = allowed_types();
+    if !allowed.iter().any(|name| file_type.matches_env_name(name)) {
+        return Err(IngestError::UnsupportedType);
+    }
+
+    match file_type {
+        ReportFileType::Png | ReportFileType::Jpeg => strip_and_preview(bytes, file_type),
+        ReportFileType::Pdf | ReportFileType::Dicom => Ok(IngestedFile {
+            bytes,
+            thumbnail: None,
+        }),
+    }
+}
+
+fn strip_and_preview(bytes: Vec<u8>, file_type: ReportFileType) -> Result<IngestedFile, IngestError> {
+    let image = image::load_from_memory(&bytes).map_err(|e| IngestError::ImageProcessing(e.to_string()))?;
+
+    // Re-encoding through `image` from decoded pixels drops any embedded
+    // EXIF/GPS/device-serial metadata the original file carried.
+    let format = match file_type {
+        ReportFileType::Png => image::ImageFormat::Png,
+        ReportFileType::Jpeg => image::ImageFormat::Jpeg,
+        _ => unreachable!("strip_and_preview is only called for PNG/JPEG"),
+    };
+    let mut stripped = Cursor::new(Vec::new());
+    image
+        .write_to(&mut stripped, format)
+        .map_err(|e| IngestError::ImageProcessing(e.to_string()))?;
+
+    let thumbnail = image.thumbnail(THUMBNAIL_MAX_DIMENSION, THUMBNAIL_MAX_DIMENSION);
+    let rgba = thumbnail.to_rgba8();
+    let blurhash = blurhash::encode(4, 3, thumbnail.width(), thumbnail.height(), &rgba)
+        .map_err(|e| IngestError::ImageProcessing(format!("blurhash encoding failed: {}", e)))?;
+
+    let mut thumbnail_bytes = Cursor::new(Vec::new());
+    thumbnail
+        .write_to(&mut thumbnail_bytes, image::ImageFormat::Png)
+        .map_err(|e| IngestError::ImageProcessing(e.to_string()))?;
+
+    Ok(IngestedFile {
+        bytes: stripped.into_inner(),
+        thumbnail: Some(ThumbnailAsset {
+            bytes: thumbnail_bytes.into_inner(),
+            blurhash,
+        }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniffs_pdf_magic_bytes() {
+        let mut bytes = b"%PDF-1.4".to_vec();
+        bytes.extend_from_slice(&[0u8; 16]);
+        assert_eq!(sniff_file_type(&bytes), Some(ReportFileType::Pdf));
+    }
+
+    #[test]
+    fn rejects_unrecognized_magic_bytes() {
+        let bytes = vec![0u8; 32];
+        assert_eq!(sniff_file_type(&bytes), None);
+    }
+
+    #[test]
+    fn ingest_rejects_unsupported_types() {
+        let bytes = vec![0u8; 32];
+        let result = ingest(bytes);
+        assert!(matches!(result, Err(IngestError::UnsupportedType)));
+    }
+}
@@ -0,0 +1,213 @@
+// --- IPFS endpoint configuration and client ---
+//
+// The Kubo API URL, an optional auth header (for a hosted pinning service),
+// and the public gateway used to build shareable links all used to be
+// hard-coded to a local node, which breaks any non-local deployment. They
+// are now read once from the environment into `IpfsConfig`, held in
+// `AppState`, and every `add` is followed by a `pin/add` call so content
+// isn't garbage-collected; if the primary node is unreachable, a configured
+// secondary endpoint is tried before giving up.
+
+use std::fmt;
+
+use reqwest::Client;
+
+const DEFAULT_API_URL: &str = "http://127.0.0.1:5001";
+const DEFAULT_GATEWAY_URL: &str = "https://ipfs.io";
+
+#[derive(Debug, Clone)]
+pub struct IpfsConfig {
+    pub api_url: String,
+    pub secondary_api_url: Option<String>,
+    pub auth_header: Option<String>,
+    pub gateway_url: String,
+}
+
+impl IpfsConfig {
+    /// Build from `.env`/the environment: `IPFS_API_URL` (default a local
+    /// Kubo node), optional `IPFS_API_URL_SECONDARY` to fall back to if the
+    /// primary is unreachable, optional `IPFS_AUTH_HEADER` sent as-is as the
+    /// `Authorization` header, and `IPFS_GATEWAY_URL` for building public
+    /// links back to the frontend.
+    pub fn from_env() -> Self {
+        IpfsConfig {
+            api_url: std::env::var("IPFS_API_URL").unwrap_or_else(|_| DEFAULT_API_URL.to_string()),
+            secondary_api_url: std::env::var("IPFS_API_URL_SECONDARY").ok(),
+            auth_header: std::env::var("IPFS_AUTH_HEADER").ok(),
+            gateway_url: std::env::var("IPFS_GATEWAY_URL")
+                .unwrap_or_else(|_| DEFAULT_GATEWAY_URL.to_string()),
+        }
+    }
+
+    /// The resolvable gateway link for `cid`.
+    pub fn gateway_link(&self, cid: &str) -> String {
+        format!("{}/ipfs/{}", self.gateway_url.trim_end_matches('/'), cid)
+    }
+}
+
+#[derive(Debug)]
+pub enum IpfsError {
+    Unreachable(String),
+    BadStatus(reqwest::StatusCode),
+    Malformed(String),
+}
+
+impl fmt::Display for IpfsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpfsError::Unreachable(msg) => write!(f, "could not reach IPFS: {}", msg),
+            IpfsError::BadStatus(status) => write!(f, "IPFS returned status {}", status),
+            IpfsError::Malformed(msg) => write!(f, "unexpected IPFS response: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for IpfsError {}
+
+fn with_auth(builder: reqwest::RequestBuilder, config: &IpfsConfig) -> reqwest::RequestBuilder {
+    match &config.auth_header {
+        Some(header) => builder.header(reqwest::header::AUTHORIZATION, header),
+        None => builder,
+    }
+}
+
+/// Add `bytes` to IPFS under `file_name`, falling back to the configured
+/// secondary endpoint if the primary is unreachable, then pin the result so
+/// it survives the node's garbage collector. Returns the CID.
+pub async fn add_and_pin(
+    client: &Client,
+    config: &IpfsConfig,
+    bytes: Vec<u8>,
+    file_name: &str,
+) -> Result<String, IpfsError> {
+    let (base_url, cid) = match add_to(client, &config.api_url, config, bytes.clone(), file_name).await {
+        Ok(cid) => (config.api_url.as_str(), cid),
+        Err(IpfsError::Unreachable(msg)) => match &config.secondary_api_url {
+            Some(secondary) => {
+                eprintln!("Primary IPFS endpoint unreachable ({}), falling back to secondary", msg);
+                let cid = add_to(client, secondary, config, bytes, file_name).await?;
+                (secondary.as_str(), cid)
+            }
+            None => return Err(IpfsError::Unreachable(msg)),
+        },
+        Err(e) => return Err(e),
+    };
+
+    pin(client, base_url, config, &cid).await?;
+    Ok(cid)
+}
+
+async fn add_to(
+    client: &Client,
+    base_url: &str,
+    config: &IpfsConfig,
+    bytes: Vec<u8>,
+    file_name: &str,
+) -> Result<String, IpfsError> {
+    // Default `add` (CIDv0, `raw-leaves=false`) wraps the content in a
+    // UnixFS/dag-pb node and chunks it over ~256KB, so the CID it returns
+    // essentially never matches a plain sha256 of the raw bytes. Forcing a
+    // single raw leaf (chunker sized to the whole payload) makes the CID
+    // Kubo assigns match `multihash::compute_raw_cid_v1` exactly, so
+    // `upload_report` can verify it.
+    let chunk_size = bytes.len().max(1);
+    let form = reqwest::multipart::Form::new()
+        .part("file", reqwest::multipart::Part::bytes(bytes).file_name(file_name.to_string()));
+
+    let url = format!(
+        "{}/api/v0/add?cid-version=1&raw-leaves=true&chunker=size-{}",
+        base_url, chunk_size
+    );
+
+    let res = with_auth(client.post(url), config)
+        .multipart(form)
+        .send()
+        .await
+        .map_err(|e| IpfsError::Unreachable(e.to_string()))?;
+
+    if !res.status().is_success() {
+        return Err(IpfsError::BadStatus(res.status()));
+    }
+
+    let res_json: serde_json::Value = res.json().await.map_err(|e| IpfsError::Malformed(e.to_string()))?;
+    match res_json["Hash"].as_str() {
+        Some(cid) if !cid.is_empty() => Ok(cid.to_string()),
+        _ => Err(IpfsError::Malformed("response did not contain a CID".to_string())),
+    }
+}
+
+async fn pin(client: &Client, base_url: &str, config: &IpfsConfig, cid: &str) -> Result<(), IpfsError> {
+    let res = with_auth(client.post(format!("{}/api/v0/pin/add?arg={}", base_url, cid)), config)
+        .send()
+        .await
+        .map_err(|e| IpfsError::Unreachable(e.to_string()))?;
+
+    if !res.status().is_success() {
+        return Err(IpfsError::BadStatus(res.status()));
+    }
+    Ok(())
+}
+
+/// Stream `cid`'s content from IPFS, optionally requesting a byte range,
+/// falling back to the configured secondary endpoint if the primary is
+/// unreachable.
+pub async fn cat(
+    client: &Client,
+    config: &IpfsConfig,
+    cid: &str,
+    byte_range: Option<(u64, Option<u64>)>,
+) -> Result<reqwest::Response, IpfsError> {
+    match cat_from(client, &config.api_url, config, cid, byte_range).await {
+        Ok(res) => Ok(res),
+        Err(IpfsError::Unreachable(msg)) => match &config.secondary_api_url {
+            Some(secondary) => {
+                eprintln!("Primary IPFS endpoint unreachable ({}), falling back to secondary for cat", msg);
+                cat_from(client, secondary, config, cid, byte_range).await
+            }
+            None => Err(IpfsError::Unreachable(msg)),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+async fn cat_from(
+    client: &Client,
+    base_url: &str,
+    config: &IpfsConfig,
+    cid: &str,
+    byte_range: Option<(u64, Option<u64>)>,
+) -> Result<reqwest::Response, IpfsError> {
+    let mut url = format!("{}/api/v0/cat?arg={}", base_url, cid);
+    if let Some((start, end)) = byte_range {
+        url.push_str(&format!("&offset={}", start));
+        if let Some(end) = end {
+            url.push_str(&format!("&length={}", end - start + 1));
+        }
+    }
+
+    let res = with_auth(client.post(&url), config)
+        .send()
+        .await
+        .map_err(|e| IpfsError::Unreachable(e.to_string()))?;
+
+    if !res.status().is_success() {
+        return Err(IpfsError::BadStatus(res.status()));
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gateway_link_joins_base_and_cid() {
+        let config = IpfsConfig {
+            api_url: DEFAULT_API_URL.to_string(),
+            secondary_api_url: None,
+            auth_header: None,
+            gateway_url: "https://gateway.example/".to_string(),
+        };
+        assert_eq!(config.gateway_link("QmTest"), "https://gateway.example/ipfs/QmTest");
+    }
+}
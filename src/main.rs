@@ -1,18 +1,37 @@
-use actix_web::{App, HttpServer, Responder, HttpResponse, post, get, web};
+mod db;
+mod ingest;
+mod ipfs;
+mod multihash;
+mod secure;
+mod solana;
+
+use actix_web::{App, HttpServer, Responder, HttpRequest, HttpResponse, post, get, web};
+use actix_web::http::header;
 use actix_multipart::Multipart;
 use actix_cors::Cors;
 use futures_util::stream::TryStreamExt; // Required for Multipart
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap; // Import HashMap
 use std::sync::Mutex; // Import Mutex for shared mutable state
+use std::time::{SystemTime, UNIX_EPOCH};
 use dotenv::dotenv; // To load .env file
 use reqwest::Client; // To make HTTP requests (for IPFS API)
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::signature::Keypair;
 
 // --- Shared State ---
 // Struct to hold shared mutable state
-// We store a vector of tuples (CID, Filename) for each wallet
+// Reports are persisted in `db_pool` (SQLite by default, Postgres behind the
+// `postgres` feature) instead of the `Mutex<HashMap>` this used to be, so a
+// restart doesn't lose data and reads no longer queue behind a global lock.
 struct AppState {
-    reports: Mutex<HashMap<String, Vec<(String, String)>>>,
+    db_pool: db::DbPool,
+    rpc_client: RpcClient,
+    fee_payer: Keypair,
+    // AES-256 keys agreed via the `/api/init-secure` ECDH handshake, keyed by
+    // session id so an encrypted upload can be decrypted-and-verified.
+    secure_sessions: Mutex<HashMap<String, [u8; 32]>>,
+    ipfs_config: ipfs::IpfsConfig,
 }
 
 // --- Response Structs ---
@@ -22,12 +41,45 @@ struct UploadResponse {
     message: String,
     cid: Option<String>,
     file_name: Option<String>,
+    tx_signature: Option<String>,
+    // Present only for plaintext image uploads: the CID of a separately
+    // pinned thumbnail and its blurhash placeholder.
+    thumbnail_cid: Option<String>,
+    blurhash: Option<String>,
+    // SHA-256 digest (hex) of the uploaded bytes, computed locally and
+    // checked against the CID the IPFS daemon reports. Lets a caller
+    // independently confirm content addressing.
+    digest: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)] // Add Debug and Clone
 struct ReportEntry {
     cid: String,
     file_name: String,
+    uploaded_at: String,
+    // Base64 AES-GCM nonce, present only for reports uploaded through the
+    // end-to-end encrypted flow; the client needs it to decrypt the CID's
+    // ciphertext with the AES key it derived during the handshake.
+    nonce: Option<String>,
+    thumbnail_cid: Option<String>,
+    blurhash: Option<String>,
+    // Directly fetchable `{gateway}/ipfs/{cid}` link, built from the
+    // configured `IPFS_GATEWAY_URL`.
+    gateway_url: String,
+}
+
+// --- Secure handshake structs ---
+#[derive(Deserialize, Debug)]
+struct InitSecureRequest {
+    client_public_key: String, // base64 X25519 ephemeral public key
+}
+
+#[derive(Serialize, Debug)]
+struct InitSecureResponse {
+    success: bool,
+    session_id: Option<String>,
+    server_public_key: Option<String>, // base64 X25519 ephemeral public key
+    message: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)] // Add Debug
@@ -40,6 +92,35 @@ struct GetReportsResponse {
 
 // --- Handlers ---
 
+#[post("/api/init-secure")]
+async fn init_secure(
+    app_state: web::Data<AppState>,
+    req: web::Json<InitSecureRequest>,
+) -> impl Responder {
+    match secure::perform_handshake(&req.client_public_key) {
+        Ok(handshake) => {
+            let mut sessions = app_state.secure_sessions.lock().unwrap();
+            sessions.insert(handshake.session_id.clone(), handshake.aes_key);
+
+            HttpResponse::Ok().json(InitSecureResponse {
+                success: true,
+                session_id: Some(handshake.session_id),
+                server_public_key: Some(base64::encode(handshake.server_public_key.as_bytes())),
+                message: None,
+            })
+        }
+        Err(e) => {
+            eprintln!("Secure handshake failed: {}", e);
+            HttpResponse::BadRequest().json(InitSecureResponse {
+                success: false,
+                session_id: None,
+                server_public_key: None,
+                message: Some(format!("Handshake failed: {}", e)),
+            })
+        }
+    }
+}
+
 #[post("/api/upload-report")]
 async fn upload_report(
     app_state: web::Data<AppState>, // Access the shared state
@@ -48,6 +129,7 @@ async fn upload_report(
     let mut target_wallet_address: Option<String> = None;
     let mut file_data: Option<Vec<u8>> = None;
     let mut file_name: Option<String> = None;
+    let mut session_id: Option<String> = None;
 
     // Iterate over multipart fields to extract data
     while let Some(field_result) = payload.try_next().await.transpose() {
@@ -60,12 +142,16 @@ async fn upload_report(
                     message: format!("Error parsing field: {:?}", e),
                     cid: None,
                     file_name: None,
+                    tx_signature: None,
+                    thumbnail_cid: None,
+                    blurhash: None,
+                    digest: None,
                 });
             }
         };
 
         let content_disposition = field.content_disposition();
-        let field_name = content_disposition.get_name().unwrap_or("");
+        let field_name = content_disposition.and_then(|cd| cd.get_name()).unwrap_or("");
 
         match field_name {
             "targetWalletAddress" => {
@@ -74,13 +160,40 @@ async fn upload_report(
                     target_wallet_address = Some(String::from_utf8_lossy(&bytes).to_string());
                 }
             }
+            "sessionId" => {
+                // Present only when the upload carries an encrypted body
+                // from a prior `/api/init-secure` handshake.
+                if let Some(bytes) = field.try_next().await.unwrap() {
+                    session_id = Some(String::from_utf8_lossy(&bytes).to_string());
+                }
+            }
             "file" => {
                 // Capture the filename
-                file_name = content_disposition.get_filename().map(|s| s.to_string());
-                // Read the entire file content into a Vec<u8>
+                file_name = content_disposition.and_then(|cd| cd.get_filename()).map(|s| s.to_string());
+                // Enforce the size cap while the chunks are still streaming
+                // in, rather than after buffering the whole field: otherwise
+                // an oversized body is read entirely into memory before the
+                // "too large" response is ever produced.
+                let max_upload_bytes = ingest::max_file_size_bytes();
                 let mut bytes = Vec::new();
                 while let Some(chunk) = field.try_next().await.unwrap() {
                     bytes.extend_from_slice(&chunk);
+                    if bytes.len() > max_upload_bytes {
+                        eprintln!(
+                            "Rejecting upload: streamed body already exceeds the {}-byte limit",
+                            max_upload_bytes
+                        );
+                        return HttpResponse::BadRequest().json(UploadResponse {
+                            success: false,
+                            message: format!("file exceeds the {}-byte upload limit", max_upload_bytes),
+                            cid: None,
+                            file_name,
+                            tx_signature: None,
+                            thumbnail_cid: None,
+                            blurhash: None,
+                            digest: None,
+                        });
+                    }
                 }
                 file_data = Some(bytes);
             }
@@ -101,6 +214,10 @@ async fn upload_report(
                 message: "File name is missing.".to_string(),
                 cid: None,
                 file_name: None,
+                tx_signature: None,
+                thumbnail_cid: None,
+                blurhash: None,
+                digest: None,
             });
         }
     };
@@ -114,6 +231,10 @@ async fn upload_report(
                 message: "File data is missing.".to_string(),
                 cid: None,
                 file_name: Some(file_name_str), // Return filename if available
+                tx_signature: None,
+                thumbnail_cid: None,
+                blurhash: None,
+                digest: None,
             });
         }
     };
@@ -127,91 +248,276 @@ async fn upload_report(
                 message: "Target wallet address is required.".to_string(),
                 cid: None,
                 file_name: Some(file_name_str), // Return filename if available
+                tx_signature: None,
+                thumbnail_cid: None,
+                blurhash: None,
+                digest: None,
             });
         }
     };
 
+    // The size cap is already enforced while the "file" field streamed in
+    // above, for both branches below: the plaintext path also re-checks it
+    // inside `ingest::ingest`, but the encrypted path never calls `ingest`
+    // (it must not inspect plaintext), so streaming enforcement is the only
+    // thing stopping an attacker who completes a legitimate handshake from
+    // uploading an unbounded ciphertext blob.
 
-    // --- IPFS Upload ---
     let client = Client::new();
-    let form = reqwest::multipart::Form::new()
-        .part("file", reqwest::multipart::Part::bytes(file_data_vec)
-            .file_name(file_name_str.clone())); // Clone filename for this part
-
-    let ipfs_url = "http://127.0.0.1:5001/api/v0/add";
-    let res = client.post(ipfs_url)
-        .multipart(form)
-        .send()
-        .await;
-
-    let res_json: serde_json::Value = match res {
-        Ok(r) => {
-            if !r.status().is_success() {
-                 let status = r.status();
-                 let body = r.text().await.unwrap_or_else(|_| "N/A".to_string());
-                 eprintln!("IPFS upload failed with status {}: {}", status, body);
-                 return HttpResponse::InternalServerError().json(UploadResponse {
+
+    // --- Encrypted upload: validate, never persist the plaintext ---
+    // `file_data_vec` holds the base64-encoded `nonce || ciphertext` blob;
+    // after the auth tag checks out we pin that same blob to IPFS unchanged
+    // and discard the decrypted bytes. Plaintext uploads instead go through
+    // `ingest`, which sniffs the type, strips image metadata, and builds a
+    // thumbnail/blurhash preview.
+    let (pin_bytes, nonce_b64, thumbnail_cid, blurhash) = if let Some(session_id) = &session_id {
+        let aes_key = match app_state.secure_sessions.lock().unwrap().get(session_id).copied() {
+            Some(key) => key,
+            None => {
+                return HttpResponse::BadRequest().json(UploadResponse {
                     success: false,
-                    message: format!("IPFS upload failed with status: {}", status),
+                    message: "Unknown or expired secure session.".to_string(),
                     cid: None,
                     file_name: Some(file_name_str),
-                 });
+                    tx_signature: None,
+                    thumbnail_cid: None,
+                    blurhash: None,
+                    digest: None,
+                });
             }
-            r.json().await.unwrap_or_else(|e| {
-                 eprintln!("Failed to parse IPFS response JSON: {:?}", e);
-                 serde_json::Value::Null // Return Null value on parse error
-            })
-        },
+        };
+
+        let blob = match base64::decode(&file_data_vec) {
+            Ok(blob) => blob,
+            Err(e) => {
+                return HttpResponse::BadRequest().json(UploadResponse {
+                    success: false,
+                    message: format!("Invalid base64 in encrypted upload: {}", e),
+                    cid: None,
+                    file_name: Some(file_name_str),
+                    tx_signature: None,
+                    thumbnail_cid: None,
+                    blurhash: None,
+                    digest: None,
+                });
+            }
+        };
+
+        if let Err(e) = secure::decrypt_and_verify(&aes_key, &blob) {
+            eprintln!("Encrypted upload failed decryption/auth check: {}", e);
+            return HttpResponse::BadRequest().json(UploadResponse {
+                success: false,
+                message: format!("Encrypted upload rejected: {}", e),
+                cid: None,
+                file_name: Some(file_name_str),
+                tx_signature: None,
+                thumbnail_cid: None,
+                blurhash: None,
+                digest: None,
+            });
+        }
+
+        // Each handshake is good for exactly one upload; evict it now so a
+        // client that starts a handshake and never uploads (or uploads
+        // repeatedly under the same session) can't leak an AES key per call
+        // for the life of the process.
+        app_state.secure_sessions.lock().unwrap().remove(session_id);
+
+        let nonce_b64 = base64::encode(&blob[..secure::NONCE_LEN]);
+        (blob, Some(nonce_b64), None, None)
+    } else {
+        let ingested = match ingest::ingest(file_data_vec) {
+            Ok(ingested) => ingested,
+            Err(e) => {
+                eprintln!("Rejecting upload for {}: {}", file_name_str, e);
+                return HttpResponse::BadRequest().json(UploadResponse {
+                    success: false,
+                    message: e.to_string(),
+                    cid: None,
+                    file_name: Some(file_name_str),
+                    tx_signature: None,
+                    thumbnail_cid: None,
+                    blurhash: None,
+                    digest: None,
+                });
+            }
+        };
+
+        let (thumbnail_cid, blurhash) = match ingested.thumbnail {
+            Some(thumb) => match ipfs::add_and_pin(
+                &client,
+                &app_state.ipfs_config,
+                thumb.bytes,
+                &format!("thumb-{}", file_name_str),
+            )
+            .await
+            {
+                Ok(cid) => (Some(cid), Some(thumb.blurhash)),
+                Err(e) => {
+                    eprintln!("Failed to pin thumbnail for {}: {}", file_name_str, e);
+                    return HttpResponse::InternalServerError().json(UploadResponse {
+                        success: false,
+                        message: format!("Failed to pin thumbnail: {}", e),
+                        cid: None,
+                        file_name: Some(file_name_str),
+                        tx_signature: None,
+                        thumbnail_cid: None,
+                        blurhash: None,
+                        digest: None,
+                    });
+                }
+            },
+            None => (None, None),
+        };
+
+        (ingested.bytes, None, thumbnail_cid, blurhash)
+    };
+
+    // Compute the digest independently of whatever the IPFS daemon reports,
+    // so a compromised or misbehaving node can't hand back a CID for
+    // content other than what was actually uploaded.
+    let digest_hex = multihash::sha256_hex(&pin_bytes);
+    let expected_cid = multihash::compute_raw_cid_v1(&pin_bytes);
+
+    // --- IPFS Upload ---
+    let cid = match ipfs::add_and_pin(&client, &app_state.ipfs_config, pin_bytes, &file_name_str).await {
+        Ok(cid) => cid,
         Err(e) => {
-            eprintln!("Failed to send request to IPFS API: {:?}", e); // Log the error
+            eprintln!("Failed to upload {} to IPFS: {}", file_name_str, e);
             return HttpResponse::InternalServerError().json(UploadResponse {
                 success: false,
                 message: format!("Failed to upload to IPFS: {}", e),
                 cid: None,
-                file_name: Some(file_name_str), // Return filename if available
+                file_name: Some(file_name_str),
+                tx_signature: None,
+                thumbnail_cid: None,
+                blurhash: None,
+                digest: Some(digest_hex),
             });
         }
     };
 
-    let cid = res_json["Hash"].as_str().unwrap_or("").to_string();
-
-    if cid.is_empty() {
-         eprintln!("CID not found in IPFS response: {:?}", res_json);
-         return HttpResponse::InternalServerError().json(UploadResponse {
+    if cid != expected_cid {
+        eprintln!(
+            "CID mismatch for {}: IPFS returned {} but the locally computed CID is {}",
+            file_name_str, cid, expected_cid
+        );
+        return HttpResponse::InternalServerError().json(UploadResponse {
             success: false,
-            message: "Failed to get CID from IPFS response.".to_string(),
+            message: "IPFS returned a CID that does not match the uploaded content.".to_string(),
             cid: None,
             file_name: Some(file_name_str),
-         });
+            tx_signature: None,
+            thumbnail_cid: None,
+            blurhash: None,
+            digest: Some(digest_hex),
+        });
     }
 
     println!("Successfully uploaded to IPFS, CID: {}", cid);
 
-    // --- Simulate Storing in Shared State (Temporary) ---
-    let mut reports_map = app_state.reports.lock().unwrap();
-    let user_reports = reports_map.entry(target_wallet.clone()).or_insert_with(Vec::new);
-    user_reports.push((cid.clone(), file_name_str.clone())); // Store tuple (CID, filename)
-    println!("Stored (CID, filename) in backend map for {}: ({}, {})", target_wallet, cid, file_name_str);
+    // --- Record the wallet -> CID link on-chain, then persist it locally ---
+    // On-chain first: if the transaction fails we bail out before touching
+    // `db`, so a row never exists for a CID that isn't actually backed by
+    // the on-chain record `get_reports` relies on it matching.
+    //
+    // `record_cid_on_chain` blocks on the RPC round-trip, so it runs on the
+    // blocking thread pool via `web::block` rather than stalling the actix
+    // worker thread that's also juggling other requests.
+    let app_state_for_rpc = app_state.clone();
+    let target_wallet_for_rpc = target_wallet.clone();
+    let cid_for_rpc = cid.clone();
+    let file_name_for_rpc = file_name_str.clone();
+    let nonce_for_rpc = nonce_b64.clone();
+    let record_result = web::block(move || {
+        solana::record_cid_on_chain(
+            &app_state_for_rpc.rpc_client,
+            &app_state_for_rpc.fee_payer,
+            &target_wallet_for_rpc,
+            &cid_for_rpc,
+            &file_name_for_rpc,
+            nonce_for_rpc.as_deref(),
+        )
+    })
+    .await;
 
+    let tx_signature = match record_result {
+        Ok(Ok(signature)) => Some(signature.to_string()),
+        Ok(Err(e)) => {
+            eprintln!("Failed to record CID on-chain for {}: {}", target_wallet, e);
+            return HttpResponse::InternalServerError().json(UploadResponse {
+                success: false,
+                message: format!("Failed to record CID on-chain: {}", e),
+                cid: Some(cid),
+                file_name: Some(file_name_str),
+                tx_signature: None,
+                thumbnail_cid: None,
+                blurhash: None,
+                digest: Some(digest_hex),
+            });
+        }
+        Err(e) => {
+            eprintln!("Blocking task recording CID on-chain for {} panicked: {}", target_wallet, e);
+            return HttpResponse::InternalServerError().json(UploadResponse {
+                success: false,
+                message: "Failed to record CID on-chain".to_string(),
+                cid: Some(cid),
+                file_name: Some(file_name_str),
+                tx_signature: None,
+                thumbnail_cid: None,
+                blurhash: None,
+                digest: Some(digest_hex),
+            });
+        }
+    };
 
-    // TODO: Implement interaction with Solana smart contract:
-    // - Use `target_wallet` (as a String) and `cid` (as a String)
-    // - You'll need to convert `target_wallet` to a Solana `Pubkey` (using `bs58`)
-    // - Construct a transaction that calls an instruction on your smart contract
-    // - This instruction should record the link between the user's Pubkey and the CID
-    // - Sign and send the transaction using a secure keypair on the backend
+    let uploaded_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        .to_string();
+
+    if let Err(e) = db::insert_report(
+        &app_state.db_pool,
+        &target_wallet,
+        &cid,
+        &file_name_str,
+        &uploaded_at,
+        nonce_b64.as_deref(),
+        thumbnail_cid.as_deref(),
+        blurhash.as_deref(),
+    )
+    .await
+    {
+        eprintln!("Failed to persist report row for {}: {}", target_wallet, e);
+        return HttpResponse::InternalServerError().json(UploadResponse {
+            success: false,
+            message: format!("Failed to persist report: {}", e),
+            cid: Some(cid),
+            file_name: Some(file_name_str),
+            tx_signature,
+            thumbnail_cid,
+            blurhash,
+            digest: Some(digest_hex),
+        });
+    }
 
+    println!("Recorded CID on-chain for {}: ({}, {}), tx {:?}", target_wallet, cid, file_name_str, tx_signature);
 
-    // Send a success response back to the frontend with the real CID and filename
+    // Send a success response back to the frontend with the real CID, filename and tx signature
     HttpResponse::Ok().json(UploadResponse {
         success: true,
-        message: "File uploaded to IPFS and recorded (simulated)".to_string(),
+        message: "File uploaded to IPFS and recorded on-chain".to_string(),
         cid: Some(cid),
         file_name: Some(file_name_str),
+        tx_signature,
+        thumbnail_cid,
+        blurhash,
+        digest: Some(digest_hex),
     })
 }
 
-
 #[get("/api/get-reports/{walletAddress}")]
 async fn get_reports(
     app_state: web::Data<AppState>, // Access the shared state
@@ -220,48 +526,190 @@ async fn get_reports(
     let wallet_address = path.into_inner();
     println!("Backend received request for reports for wallet: {}", wallet_address);
 
-    // Retrieve reports from the shared state (simulation of fetching from blockchain)
-    let reports_map = app_state.reports.lock().unwrap();
-    let user_reports_tuples = reports_map.get(&wallet_address);
-
-    match user_reports_tuples {
-        Some(reports_tuples) => {
-            println!("Found {} reports for wallet: {}", reports_tuples.len(), wallet_address);
-            // Convert the vector of tuples to a vector of ReportEntry structs
-            let reports_list: Vec<ReportEntry> = reports_tuples.iter()
-                .map(|(cid, filename)| ReportEntry {
-                    cid: cid.clone(),
-                    file_name: filename.clone(),
+    // Read the CIDs recorded for this wallet from the `reports` table, which
+    // is now the durable, queryable copy `get_reports` serves.
+    match db::fetch_reports(&app_state.db_pool, &wallet_address).await {
+        Ok(rows) => {
+            println!("Found {} reports for wallet: {}", rows.len(), wallet_address);
+            let reports_list: Vec<ReportEntry> = rows
+                .into_iter()
+                .map(|row| ReportEntry {
+                    gateway_url: app_state.ipfs_config.gateway_link(&row.cid),
+                    cid: row.cid,
+                    file_name: row.file_name,
+                    uploaded_at: row.uploaded_at,
+                    nonce: row.nonce,
+                    thumbnail_cid: row.thumbnail_cid,
+                    blurhash: row.blurhash,
                 })
                 .collect();
 
+            let message = if reports_list.is_empty() {
+                Some("No reports found for this wallet".to_string())
+            } else {
+                None
+            };
+
             HttpResponse::Ok().json(GetReportsResponse {
                 success: true,
-                reports: reports_list, // Return the list of ReportEntry structs
-                message: None,
+                reports: reports_list,
+                message,
             })
         }
-        None => {
-            println!("No reports found for wallet: {}", wallet_address);
-            HttpResponse::Ok().json(GetReportsResponse {
-                success: true,
-                reports: vec![], // Return empty if none found
-                message: Some("No reports found for this wallet".to_string()),
+        Err(e) => {
+            eprintln!("Failed to read persisted reports for {}: {}", wallet_address, e);
+            HttpResponse::InternalServerError().json(GetReportsResponse {
+                success: false,
+                reports: vec![],
+                message: Some(format!("Failed to read persisted reports: {}", e)),
             })
         }
     }
 }
 
 
+/// Parse a single-range `Range: bytes=start-end` header value. Multi-range
+/// requests aren't supported; anything else, or a range with `end < start`,
+/// disables ranged transfer rather than handing `cat_from` a span that
+/// underflows computing its length.
+fn parse_byte_range(range_header: &str) -> Option<(u64, Option<u64>)> {
+    let spec = range_header.strip_prefix("bytes=")?;
+    let (start_str, end_str) = spec.split_once('-')?;
+    let start: u64 = start_str.parse().ok()?;
+    let end = if end_str.is_empty() {
+        None
+    } else {
+        let end: u64 = end_str.parse().ok()?;
+        if end < start {
+            return None;
+        }
+        Some(end)
+    };
+    Some((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_bounded_range() {
+        assert_eq!(parse_byte_range("bytes=100-199"), Some((100, Some(199))));
+    }
+
+    #[test]
+    fn parses_an_open_ended_range() {
+        assert_eq!(parse_byte_range("bytes=100-"), Some((100, None)));
+    }
+
+    #[test]
+    fn rejects_an_end_before_start() {
+        assert_eq!(parse_byte_range("bytes=100-10"), None);
+    }
+
+    #[test]
+    fn rejects_a_malformed_header() {
+        assert_eq!(parse_byte_range("not-a-range"), None);
+    }
+}
+
+#[get("/api/report-file/{walletAddress}/{cid}")]
+async fn get_report_file(
+    app_state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    req: HttpRequest,
+) -> impl Responder {
+    let (wallet_address, cid) = path.into_inner();
+
+    // Verify the CID actually belongs to this wallet before proxying anything.
+    let rows = match db::fetch_reports(&app_state.db_pool, &wallet_address).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            eprintln!("Failed to look up reports for {}: {}", wallet_address, e);
+            return HttpResponse::InternalServerError().body("Failed to look up reports for this wallet.");
+        }
+    };
+
+    let report = match rows.into_iter().find(|row| row.cid == cid) {
+        Some(row) => row,
+        None => {
+            return HttpResponse::NotFound().body("That CID is not recorded for this wallet.");
+        }
+    };
+
+    let byte_range = req
+        .headers()
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_byte_range);
+
+    let client = Client::new();
+    let ipfs_res = match ipfs::cat(&client, &app_state.ipfs_config, &cid, byte_range).await {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Failed to fetch CID {} from IPFS: {}", cid, e);
+            return HttpResponse::InternalServerError().body(format!("Failed to fetch the file from IPFS: {}", e));
+        }
+    };
+
+    let byte_stream = ipfs_res
+        .bytes_stream()
+        .map_err(|e| actix_web::error::ErrorInternalServerError(format!("IPFS stream error: {}", e)));
+
+    let content_disposition = format!("inline; filename=\"{}\"", report.file_name);
+
+    if let Some((start, end)) = byte_range {
+        let mut response = HttpResponse::PartialContent();
+        response
+            .insert_header((header::ACCEPT_RANGES, "bytes"))
+            .insert_header((header::CONTENT_DISPOSITION, content_disposition));
+        // `db` doesn't record the report's total length, so an open-ended
+        // range (`bytes=100-`) has no real upper bound to report. Per RFC
+        // 7233 the range-resp-spec must be a concrete `first-last` pair or a
+        // literal `*` for the whole spec — `100-*` is neither, so omit the
+        // header entirely rather than emit something a range-aware client
+        // can't parse.
+        if let Some(end) = end {
+            response.insert_header((header::CONTENT_RANGE, format!("bytes {}-{}/*", start, end)));
+        }
+        response.streaming(byte_stream)
+    } else {
+        HttpResponse::Ok()
+            .insert_header((header::ACCEPT_RANGES, "bytes"))
+            .insert_header((header::CONTENT_DISPOSITION, content_disposition))
+            .streaming(byte_stream)
+    }
+}
+
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok(); // Load .env file
 
     println!("Starting Rust backend server on http://127.0.0.1:3001");
 
+    let solana_rpc_url = std::env::var("SOLANA_RPC_URL")
+        .unwrap_or_else(|_| "http://127.0.0.1:8899".to_string());
+    let rpc_client = RpcClient::new(solana_rpc_url);
+    let fee_payer = solana::load_fee_payer().expect(
+        "failed to load Solana fee-payer keypair from SOLANA_FEE_PAYER_PATH or SOLANA_FEE_PAYER_SECRET",
+    );
+
+    let database_url = std::env::var("DATABASE_URL")
+        .unwrap_or_else(|_| "sqlite://reports.db?mode=rwc".to_string());
+    let db_pool = db::init_pool(&database_url)
+        .await
+        .expect("failed to connect to the reports database");
+
+    let ipfs_config = ipfs::IpfsConfig::from_env();
+
     // Create and configure the shared state
     let app_state = web::Data::new(AppState {
-        reports: Mutex::new(HashMap::new()),
+        db_pool,
+        rpc_client,
+        fee_payer,
+        secure_sessions: Mutex::new(HashMap::new()),
+        ipfs_config,
     });
 
     HttpServer::new(move || { // Use 'move' to move app_state into the closure
@@ -274,10 +722,12 @@ async fn main() -> std::io::Result<()> {
         App::new()
             .app_data(app_state.clone()) // Register shared state
             .wrap(cors) // Add CORS middleware
+            .service(init_secure)   // Register the ECDH handshake endpoint
             .service(upload_report) // Register the upload endpoint
             .service(get_reports)    // Register the get reports endpoint
+            .service(get_report_file) // Register the streamed file download endpoint
     })
     .bind("127.0.0.1:3001")? // Bind to the correct address and port
     .run()
     .await
-}
\ No newline at end of file
+}
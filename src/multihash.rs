@@ -0,0 +1,109 @@
+// --- Content-address verification ---
+//
+// Kubo's default `add` (CIDv0, `raw-leaves=false`) wraps every file — even
+// single-chunk ones — in a UnixFS/dag-pb node before hashing, and splits
+// anything over ~256KB into a DAG of multiple hashed blocks. A plain
+// `base58(multihash(sha256(raw_bytes)))` essentially never equals the CID
+// such a node returns. To make local verification actually match, `ipfs`
+// (see ipfs.rs) requests `add` with `cid-version=1&raw-leaves=true` and a
+// `chunker` sized to the whole payload, so Kubo stores the file as a single
+// raw leaf instead of a UnixFS DAG — which is exactly what `compute_raw_cid_v1`
+// computes here.
+
+use sha2::{Digest, Sha256};
+
+/// CIDv1 version byte (varint 1).
+const CID_V1: u8 = 0x01;
+/// Multicodec for a raw (unwrapped) block.
+const MULTICODEC_RAW: u8 = 0x55;
+/// Multihash prefix for sha2-256: function code 0x12, 32-byte digest length.
+const SHA2_256_MULTIHASH_PREFIX: [u8; 2] = [0x12, 0x20];
+
+const BASE32_LOWER_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// The SHA-256 digest of `bytes`, hex-encoded, for display independent of
+/// any particular CID version.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The CIDv1 (base32 lower, raw codec, sha2-256 multihash) IPFS assigns to
+/// `bytes` when stored as a single raw leaf (`raw-leaves=true`, chunked so
+/// the whole payload fits in one block).
+pub fn compute_raw_cid_v1(bytes: &[u8]) -> String {
+    let digest = Sha256::digest(bytes);
+
+    let mut cid_bytes = Vec::with_capacity(2 + SHA2_256_MULTIHASH_PREFIX.len() + digest.len());
+    cid_bytes.push(CID_V1);
+    cid_bytes.push(MULTICODEC_RAW);
+    cid_bytes.extend_from_slice(&SHA2_256_MULTIHASH_PREFIX);
+    cid_bytes.extend_from_slice(&digest);
+
+    format!("b{}", base32_lower_no_pad(&cid_bytes))
+}
+
+/// RFC 4648 base32, lowercase alphabet, no padding — the default multibase
+/// IPFS uses to print a CIDv1 (the leading "b").
+fn base32_lower_no_pad(bytes: &[u8]) -> String {
+    let mut output = String::with_capacity((bytes.len() * 8).div_ceil(5));
+    let mut buffer: u32 = 0;
+    let mut bits_in_buffer = 0u32;
+
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits_in_buffer += 8;
+        while bits_in_buffer >= 5 {
+            bits_in_buffer -= 5;
+            let index = (buffer >> bits_in_buffer) & 0x1F;
+            output.push(BASE32_LOWER_ALPHABET[index as usize] as char);
+        }
+    }
+    if bits_in_buffer > 0 {
+        let index = (buffer << (5 - bits_in_buffer)) & 0x1F;
+        output.push(BASE32_LOWER_ALPHABET[index as usize] as char);
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compute_raw_cid_v1_is_deterministic() {
+        let bytes = b"patient report bytes";
+        assert_eq!(compute_raw_cid_v1(bytes), compute_raw_cid_v1(bytes));
+    }
+
+    #[test]
+    fn compute_raw_cid_v1_differs_for_different_content() {
+        assert_ne!(compute_raw_cid_v1(b"report a"), compute_raw_cid_v1(b"report b"));
+    }
+
+    #[test]
+    fn compute_raw_cid_v1_starts_with_the_cidv1_multibase_prefix() {
+        // "b" is the multibase prefix for base32-lower; CIDv1 raw-leaf CIDs
+        // Kubo prints all start with "bafkrei..." for small payloads.
+        assert!(compute_raw_cid_v1(b"patient report bytes").starts_with("bafkrei"));
+    }
+
+    #[test]
+    fn compute_raw_cid_v1_is_deterministic_for_a_payload_over_256kb() {
+        // Regression guard for the Kubo chunking boundary: a payload this
+        // size would be split into multiple UnixFS blocks under Kubo's
+        // defaults, which is exactly why `ipfs::add_and_pin` forces
+        // `raw-leaves=true` with a whole-payload chunker so it stays a
+        // single block that this function's hash can match.
+        let bytes = vec![0x42u8; 300_000];
+        assert_eq!(compute_raw_cid_v1(&bytes), compute_raw_cid_v1(&bytes));
+        assert_ne!(compute_raw_cid_v1(&bytes), compute_raw_cid_v1(b"patient report bytes"));
+    }
+
+    #[test]
+    fn sha256_hex_is_64_lowercase_hex_chars() {
+        let digest = sha256_hex(b"patient report bytes");
+        assert_eq!(digest.len(), 64);
+        assert!(digest.chars().all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()));
+    }
+}
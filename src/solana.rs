@@ -0,0 +1,220 @@
+// --- Solana on-chain CID recording ---
+//
+// Every upload's (patient, CID) link is recorded on-chain in a PDA derived
+// from the patient's pubkey, giving patients a tamper-evident record of
+// every CID ever linked to their wallet that doesn't depend on trusting
+// this backend's own database. Nothing here ever reads the PDA back — it's
+// a write-only audit trail, not a query path.
+//
+// The module is split into "build" (pure, no network/IO) and "send" halves,
+// mirroring how Solana's own wallet/CLI code separates instruction
+// construction from submission so the former can be unit tested without a
+// running validator.
+
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    transaction::Transaction,
+};
+use solana_system_interface::program as system_program;
+
+/// Program that owns the per-patient "reports" PDA.
+///
+/// Configurable so the same binary can point at devnet/mainnet deployments
+/// of the recorder program without a rebuild.
+fn program_id() -> Result<Pubkey, SolanaError> {
+    let raw = env::var("DN_RECORDER_PROGRAM_ID")
+        .map_err(|_| SolanaError::MissingConfig("DN_RECORDER_PROGRAM_ID"))?;
+    Pubkey::from_str(&raw).map_err(|_| SolanaError::InvalidPubkey(raw))
+}
+
+/// One (cid, file_name) link recorded for a patient.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReportRecord {
+    pub cid: String,
+    pub file_name: String,
+    /// Base64-encoded AES-GCM nonce, present only for reports uploaded
+    /// through the end-to-end encrypted flow.
+    pub nonce: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum SolanaError {
+    MissingConfig(&'static str),
+    InvalidPubkey(String),
+    InvalidKeypair(String),
+    Rpc(String),
+}
+
+impl fmt::Display for SolanaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolanaError::MissingConfig(var) => write!(f, "missing env var {}", var),
+            SolanaError::InvalidPubkey(raw) => write!(f, "invalid pubkey: {}", raw),
+            SolanaError::InvalidKeypair(msg) => write!(f, "invalid fee-payer keypair: {}", msg),
+            SolanaError::Rpc(msg) => write!(f, "solana rpc error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for SolanaError {}
+
+/// Decode a base58 patient wallet address into a `Pubkey`.
+pub fn parse_patient_wallet(target_wallet: &str) -> Result<Pubkey, SolanaError> {
+    Pubkey::from_str(target_wallet).map_err(|_| SolanaError::InvalidPubkey(target_wallet.to_string()))
+}
+
+/// Derive the PDA that stores `patient`'s recorded CIDs.
+pub fn derive_reports_pda(patient: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"reports", patient.as_ref()], program_id)
+}
+
+/// Load the backend's fee-payer keypair, either from a JSON keypair file
+/// (`SOLANA_FEE_PAYER_PATH`, the `solana-keygen` format) or a raw base58
+/// secret key (`SOLANA_FEE_PAYER_SECRET`), mirroring the two ways the
+/// Solana CLI itself accepts a signer.
+pub fn load_fee_payer() -> Result<Keypair, SolanaError> {
+    if let Ok(path) = env::var("SOLANA_FEE_PAYER_PATH") {
+        let bytes = std::fs::read_to_string(&path)
+            .map_err(|e| SolanaError::InvalidKeypair(format!("reading {}: {}", path, e)))?;
+        let raw: Vec<u8> = serde_json::from_str(&bytes)
+            .map_err(|e| SolanaError::InvalidKeypair(format!("parsing {}: {}", path, e)))?;
+        return Keypair::try_from(raw.as_slice())
+            .map_err(|e| SolanaError::InvalidKeypair(e.to_string()));
+    }
+
+    if let Ok(secret) = env::var("SOLANA_FEE_PAYER_SECRET") {
+        let raw = bs58::decode(&secret)
+            .into_vec()
+            .map_err(|e| SolanaError::InvalidKeypair(e.to_string()))?;
+        return Keypair::try_from(raw.as_slice())
+            .map_err(|e| SolanaError::InvalidKeypair(e.to_string()));
+    }
+
+    Err(SolanaError::MissingConfig(
+        "SOLANA_FEE_PAYER_PATH or SOLANA_FEE_PAYER_SECRET",
+    ))
+}
+
+/// Build the instruction that records `(patient, cid)` on-chain. Pure and
+/// network-free so it can be asserted against directly in tests, and fed to
+/// `solana-program-test`/a local validator without going through the RPC path.
+pub fn build_record_cid_instruction(
+    program_id: &Pubkey,
+    fee_payer: &Pubkey,
+    patient: &Pubkey,
+    cid: &str,
+    file_name: &str,
+    nonce: Option<&str>,
+) -> (Instruction, Pubkey) {
+    let (reports_pda, bump) = derive_reports_pda(patient, program_id);
+
+    let mut data = vec![0u8]; // instruction discriminant: 0 = RecordCid
+    data.push(bump);
+    BorshSerialize::serialize(
+        &ReportRecord {
+            cid: cid.to_string(),
+            file_name: file_name.to_string(),
+            nonce: nonce.map(|n| n.to_string()),
+        },
+        &mut data,
+    )
+    .expect("serializing ReportRecord cannot fail");
+
+    let instruction = Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(reports_pda, false),
+            AccountMeta::new(*fee_payer, true),
+            AccountMeta::new_readonly(*patient, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data,
+    };
+
+    (instruction, reports_pda)
+}
+
+/// Sign and submit the record-cid instruction, returning the transaction
+/// signature once it has been confirmed.
+pub fn record_cid_on_chain(
+    rpc_client: &RpcClient,
+    fee_payer: &Keypair,
+    target_wallet: &str,
+    cid: &str,
+    file_name: &str,
+    nonce: Option<&str>,
+) -> Result<Signature, SolanaError> {
+    let program_id = program_id()?;
+    let patient = parse_patient_wallet(target_wallet)?;
+    let (instruction, _reports_pda) = build_record_cid_instruction(
+        &program_id,
+        &fee_payer.pubkey(),
+        &patient,
+        cid,
+        file_name,
+        nonce,
+    );
+
+    let recent_blockhash = rpc_client
+        .get_latest_blockhash()
+        .map_err(|e| SolanaError::Rpc(e.to_string()))?;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&fee_payer.pubkey()),
+        &[fee_payer],
+        recent_blockhash,
+    );
+
+    rpc_client
+        .send_and_confirm_transaction(&transaction)
+        .map_err(|e| SolanaError::Rpc(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_record_cid_instruction_targets_the_derived_pda() {
+        let program_id = Pubkey::new_unique();
+        let fee_payer = Pubkey::new_unique();
+        let patient = Pubkey::new_unique();
+
+        let (instruction, reports_pda) = build_record_cid_instruction(
+            &program_id,
+            &fee_payer,
+            &patient,
+            "Qm123",
+            "scan.pdf",
+            None,
+        );
+
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts[0].pubkey, reports_pda);
+        assert!(instruction.accounts[0].is_writable);
+        assert_eq!(instruction.accounts[1].pubkey, fee_payer);
+        assert!(instruction.accounts[1].is_signer);
+        assert_eq!(instruction.accounts[2].pubkey, patient);
+    }
+
+    #[test]
+    fn derive_reports_pda_is_stable_for_the_same_patient() {
+        let program_id = Pubkey::new_unique();
+        let patient = Pubkey::new_unique();
+
+        let (pda_a, bump_a) = derive_reports_pda(&patient, &program_id);
+        let (pda_b, bump_b) = derive_reports_pda(&patient, &program_id);
+
+        assert_eq!(pda_a, pda_b);
+        assert_eq!(bump_a, bump_b);
+    }
+}